@@ -1,25 +1,166 @@
 use once_cell::sync::Lazy;
+use serde::Deserialize;
 use std::env;
+use std::time::Duration;
+
+const CONFIG_PATH: &str = "config.yaml";
+const DEFAULT_CONNECT_TIMEOUT_MS: u64 = 10_000;
+
+/// Session-level parameters that shape how the assistant behaves, loaded from
+/// `config.yaml` so operators can rebrand the assistant without a rebuild.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct SessionSettings {
+    pub voice: String,
+    pub instructions: String,
+    pub model: String,
+    pub modalities: Vec<String>,
+    pub transcription_model: String,
+}
+
+impl Default for SessionSettings {
+    fn default() -> Self {
+        Self {
+            voice: "alloy".to_string(),
+            instructions:
+                "You are a helpful AI assistant. Have a natural conversation with the user in English."
+                    .to_string(),
+            model: "gpt-4o-realtime-preview-2024-10-01".to_string(),
+            modalities: vec!["text".to_string(), "audio".to_string()],
+            transcription_model: "whisper-1".to_string(),
+        }
+    }
+}
+
+/// Which code path handles a client's audio: the low-latency Realtime
+/// WebSocket API, or the REST transcribe/chat/speech pipeline for
+/// environments where the Realtime API is unavailable or too costly.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Mode {
+    Realtime,
+    Pipeline,
+}
+
+impl Default for Mode {
+    fn default() -> Self {
+        Mode::Realtime
+    }
+}
+
+impl std::str::FromStr for Mode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "realtime" => Ok(Mode::Realtime),
+            "pipeline" => Ok(Mode::Pipeline),
+            other => Err(format!("unknown mode: {other}")),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Default)]
+#[serde(default)]
+struct FileConfig {
+    #[serde(flatten)]
+    session: SessionSettings,
+    mode: Mode,
+}
 
 // Struct to hold our application settings
 #[derive(Debug)]
 pub struct Settings {
     pub openai_api_key: String,
+    // Override the realtime endpoint, e.g. to point at an Azure OpenAI
+    // deployment or another OpenAI-compatible gateway.
+    pub base_url: Option<String>,
+    pub session: SessionSettings,
+    pub mode: Mode,
+    // When both are set, the server terminates TLS itself and serves
+    // wss://, rather than falling back to the plain listener.
+    pub tls_cert_path: Option<String>,
+    pub tls_key_path: Option<String>,
+    // Outbound HTTP/SOCKS5 proxy the OpenAI connection is tunneled through,
+    // for deployments behind a corporate firewall.
+    pub proxy: Option<String>,
+    pub connect_timeout: Duration,
 }
 
 impl Settings {
-    // Load settings from environment variables
+    // Load settings from config.yaml (if present) and environment variables,
+    // with env vars taking precedence so deployments can override per-host.
     fn load() -> Self {
         // Load .env file if it exists
         dotenvy::dotenv().ok();
 
         let openai_api_key = env::var("OPENAI_API_KEY")
             .expect("OPENAI_API_KEY must be set in .env or environment");
+        let base_url = env::var("OPENAI_BASE_URL").ok();
+        let tls_cert_path = env::var("TLS_CERT_PATH").ok();
+        let tls_key_path = env::var("TLS_KEY_PATH").ok();
+        let proxy = env::var("PROXY")
+            .ok()
+            .or_else(|| env::var("HTTPS_PROXY").ok())
+            .or_else(|| env::var("ALL_PROXY").ok());
+        let connect_timeout = env::var("CONNECT_TIMEOUT_MS")
+            .ok()
+            .and_then(|ms| ms.parse().ok())
+            .map(Duration::from_millis)
+            .unwrap_or(Duration::from_millis(DEFAULT_CONNECT_TIMEOUT_MS));
+
+        let FileConfig {
+            session: mut session,
+            mode: mut mode,
+        } = std::fs::read_to_string(CONFIG_PATH)
+            .ok()
+            .and_then(|contents| match serde_yaml::from_str::<FileConfig>(&contents) {
+                Ok(config) => Some(config),
+                Err(e) => {
+                    tracing::warn!("Failed to parse {}: {}", CONFIG_PATH, e);
+                    None
+                }
+            })
+            .unwrap_or_default();
+
+        if let Ok(voice) = env::var("VOICE") {
+            session.voice = voice;
+        }
+        if let Ok(instructions) = env::var("INSTRUCTIONS") {
+            session.instructions = instructions;
+        }
+        if let Ok(model) = env::var("OPENAI_MODEL") {
+            session.model = model;
+        }
+        if let Ok(modalities) = env::var("MODALITIES") {
+            session.modalities = modalities
+                .split(',')
+                .map(|m| m.trim().to_string())
+                .collect();
+        }
+        if let Ok(transcription_model) = env::var("TRANSCRIPTION_MODEL") {
+            session.transcription_model = transcription_model;
+        }
+        if let Ok(mode_str) = env::var("MODE") {
+            match mode_str.parse() {
+                Ok(parsed) => mode = parsed,
+                Err(e) => tracing::warn!("Ignoring invalid MODE env var: {}", e),
+            }
+        }
 
-        Self { openai_api_key }
+        Self {
+            openai_api_key,
+            base_url,
+            session,
+            mode,
+            tls_cert_path,
+            tls_key_path,
+            proxy,
+            connect_timeout,
+        }
     }
 }
 
 // Create a static, lazy-loaded instance of the settings
 // This is the Rust equivalent of the module-level `settings = Settings()` in Python
-pub static SETTINGS: Lazy<Settings> = Lazy::new(Settings::load);
\ No newline at end of file
+pub static SETTINGS: Lazy<Settings> = Lazy::new(Settings::load);
@@ -1,24 +1,28 @@
 mod config;
-use config::SETTINGS;
+use config::{Mode, SETTINGS};
 mod messages;
 use messages::*;
-
+mod metrics;
+use metrics::SessionId;
+mod pipeline;
+mod provider;
+use provider::{OpenAIProvider, RealtimeProvider};
+mod proxy;
 
 use axum::{extract::{
     ws::{Message, WebSocket},
     WebSocketUpgrade,
 }, response::{Html, IntoResponse}, routing::get, Router};
-use futures_util::{sink::SinkExt, stream::StreamExt};
+use axum_server::tls_rustls::RustlsConfig;
+use futures_util::{sink::SinkExt, stream::{SplitSink, StreamExt}};
 use std::net::SocketAddr;
-use tokio_tungstenite::connect_async;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
 use tokio_tungstenite::tungstenite::Message as TungsteniteMessage;
-use tokio_tungstenite::tungstenite::client::IntoClientRequest;
 use base64::Engine;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt, EnvFilter};
 
-const OPENAI_REALTIME_URL: &str =
-    "wss://api.openai.com/v1/realtime?model=gpt-4o-realtime-preview-2024-10-01";
-
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     // Initialize tracing (logging)
@@ -33,12 +37,25 @@ async fn main() -> anyhow::Result<()> {
 
     let app = Router::new()
         .route("/", get(get_index))
-        .route("/ws", get(websocket_handler));
+        .route("/ws", get(websocket_handler))
+        .route("/stats", get(stats_handler));
 
     let addr = SocketAddr::from(([0, 0, 0, 0], 8000));
-    tracing::info!("Listening on {}", addr);
-    let listener = tokio::net::TcpListener::bind(addr).await?;
-    axum::serve(listener, app).await?;
+
+    match (&SETTINGS.tls_cert_path, &SETTINGS.tls_key_path) {
+        (Some(cert_path), Some(key_path)) => {
+            tracing::info!("Listening on wss://{} (TLS enabled)", addr);
+            let tls_config = RustlsConfig::from_pem_file(cert_path, key_path).await?;
+            axum_server::bind_rustls(addr, tls_config)
+                .serve(app.into_make_service())
+                .await?;
+        }
+        _ => {
+            tracing::info!("Listening on {}", addr);
+            let listener = tokio::net::TcpListener::bind(addr).await?;
+            axum::serve(listener, app).await?;
+        }
+    }
 
     Ok(())
 }
@@ -60,58 +77,297 @@ async fn websocket_handler(ws: WebSocketUpgrade) -> impl IntoResponse {
     ws.on_upgrade(handle_client_socket)
 }
 
-/// Main logic for handling a single client WebSocket
-async fn handle_client_socket(mut client_ws: WebSocket) {
-    tracing::info!("Client connected");
+/// Handle dashboard connections to `/stats`, pushing an aggregated JSON
+/// snapshot of all live sessions' metrics once a second.
+async fn stats_handler(ws: WebSocketUpgrade) -> impl IntoResponse {
+    ws.on_upgrade(handle_stats_socket)
+}
+
+async fn handle_stats_socket(mut socket: WebSocket) {
+    tracing::info!("Stats dashboard connected");
+    let mut interval = tokio::time::interval(Duration::from_secs(1));
+
+    loop {
+        interval.tick().await;
+        let snapshot = metrics::snapshot().await;
+        let payload = match serde_json::to_string(&snapshot) {
+            Ok(payload) => payload,
+            Err(e) => {
+                tracing::error!("Failed to serialize session stats: {}", e);
+                continue;
+            }
+        };
+        if socket.send(Message::Text(payload)).await.is_err() {
+            tracing::info!("Stats dashboard disconnected");
+            break;
+        }
+    }
+}
+
+/// Main logic for handling a single client WebSocket.
+///
+/// In realtime mode, a single OpenAI connection is opened for the lifetime of
+/// the client session and kept alive across turns: the model's own server-side VAD
+/// (see `turn_detection` in `SessionUpdate`) decides when an utterance ends
+/// and a response should start, so the client can just keep streaming audio.
+async fn handle_client_socket(client_ws: WebSocket) {
+    let session_id = metrics::next_session_id();
+    metrics::register_session(session_id).await;
+
+    run_client_session(client_ws, session_id).await;
+
+    metrics::remove_session(session_id).await;
+}
+
+async fn run_client_session(client_ws: WebSocket, session_id: SessionId) {
+    tracing::info!(session_id, "Client connected");
+
+    let (mut client_write, client_read) = client_ws.split();
 
-    // Send ready signal
     let ready_msg = serde_json::json!({"type": "ready"});
-    if client_ws
+    if client_write
         .send(Message::Text(ready_msg.to_string()))
         .await
         .is_err()
     {
-        tracing::error!("Failed to send ready message to client");
+        tracing::error!(session_id, "Failed to send ready message to client");
+        return;
+    }
+
+    if SETTINGS.mode == Mode::Pipeline {
+        handle_pipeline_session(client_write, client_read, session_id).await;
+        return;
+    }
+
+    tracing::info!(session_id, "Connecting to OpenAI Realtime API...");
+    let provider: Arc<dyn RealtimeProvider> = Arc::new(OpenAIProvider::new(&SETTINGS));
+
+    let request = match provider.connect_request() {
+        Ok(request) => request,
+        Err(e) => {
+            tracing::error!(session_id, "Failed to build OpenAI connect request: {:?}", e);
+            metrics::update(session_id, |s| s.errors += 1).await;
+            let _ = client_write
+                .send(Message::Text(
+                    serde_json::json!({"type": "error", "message": e.to_string()}).to_string(),
+                ))
+                .await;
+            return;
+        }
+    };
+
+    let connect_start = Instant::now();
+    let connect_result = proxy::connect(
+        request,
+        SETTINGS.proxy.as_deref(),
+        SETTINGS.connect_timeout,
+    )
+    .await;
+    let (openai_ws, _) = match connect_result {
+        Ok(conn) => conn,
+        Err(e) => {
+            tracing::error!(session_id, "Failed to connect to OpenAI Realtime API: {}", e);
+            metrics::update(session_id, |s| s.errors += 1).await;
+            let _ = client_write
+                .send(Message::Text(
+                    serde_json::json!({"type": "error", "message": e.to_string()}).to_string(),
+                ))
+                .await;
+            return;
+        }
+    };
+    metrics::update(session_id, |s| {
+        s.connect_latency_ms = Some(connect_start.elapsed().as_millis() as u64)
+    })
+    .await;
+    tracing::info!(session_id, "Connected to OpenAI Realtime API");
+
+    let (mut openai_write, openai_read) = openai_ws.split();
+
+    let session_update = match provider.session_update() {
+        Ok(session_update) => session_update,
+        Err(e) => {
+            tracing::error!(session_id, "Failed to build session.update: {:?}", e);
+            metrics::update(session_id, |s| s.errors += 1).await;
+            return;
+        }
+    };
+    if let Err(e) = openai_write
+        .send(TungsteniteMessage::Text(session_update))
+        .await
+    {
+        tracing::error!(session_id, "Failed to send session.update to OpenAI: {}", e);
+        metrics::update(session_id, |s| s.errors += 1).await;
         return;
     }
 
-    // Main loop: wait for messages from the client
-    while let Some(msg) = client_ws.recv().await {
+    let client_write = Arc::new(Mutex::new(client_write));
+    let notify_write = client_write.clone();
+
+    let mut to_openai = tokio::spawn(forward_client_to_openai(
+        client_read,
+        openai_write,
+        client_write.clone(),
+        session_id,
+    ));
+    let mut to_client = tokio::spawn(forward_openai_to_client(
+        openai_read,
+        client_write,
+        provider,
+        session_id,
+    ));
+
+    // Either direction ending (client disconnect, OpenAI connection drop)
+    // tears the whole session down: abort whichever side is still running
+    // instead of waiting for both, so a dead OpenAI connection doesn't leave
+    // `forward_client_to_openai` (and its metrics entry) running until the
+    // client happens to disconnect on its own.
+    tokio::select! {
+        _ = &mut to_openai => {
+            to_client.abort();
+        }
+        _ = &mut to_client => {
+            to_openai.abort();
+            let _ = notify_write
+                .lock()
+                .await
+                .send(Message::Text(
+                    serde_json::json!({"type": "error", "message": "OpenAI connection closed"})
+                        .to_string(),
+                ))
+                .await;
+        }
+    }
+    tracing::info!(session_id, "Client session ended");
+}
+
+/// Handles a client session using the REST transcribe/chat/speech pipeline
+/// instead of the Realtime WebSocket API. Each utterance is a self-contained
+/// request/response round-trip, so no persistent upstream connection is kept.
+async fn handle_pipeline_session(
+    mut client_write: futures_util::stream::SplitSink<WebSocket, Message>,
+    mut client_read: futures_util::stream::SplitStream<WebSocket>,
+    session_id: SessionId,
+) {
+    while let Some(msg) = client_read.next().await {
         match msg {
             Ok(Message::Binary(audio_data)) => {
-                tracing::info!("Received audio data: {} bytes", audio_data.len());
+                tracing::debug!("Received audio data: {} bytes", audio_data.len());
                 if audio_data.is_empty() {
                     tracing::warn!("Audio data is empty!");
-                    let _ = client_ws
-                        .send(Message::Text(
-                            serde_json::json!({"type": "error", "message": "Audio data is empty"})
-                                .to_string(),
-                        ))
-                        .await;
                     continue;
                 }
+                metrics::update(session_id, |s| {
+                    s.audio_bytes_received += audio_data.len() as u64
+                })
+                .await;
 
-                // Spawn a new task to handle the OpenAI connection
-                // This allows the server to process other messages from the client
-                // if needed, though in this design we process inline for simplicity.
-                if let Err(e) = handle_openai_stream(&mut client_ws, audio_data).await {
-                    tracing::error!("OpenAI stream error: {:?}", e);
-                    let _ = client_ws
-                        .send(Message::Text(
-                            serde_json::json!({"type": "error", "message": e.to_string()})
-                                .to_string(),
-                        ))
+                match pipeline::run_pipeline(&SETTINGS, audio_data).await {
+                    Ok((transcript, audio)) => {
+                        metrics::update(session_id, |s| {
+                            s.transcript_chars += transcript.chars().count() as u64
+                        })
                         .await;
+                        let complete_msg = serde_json::json!({
+                            "type": "response_complete",
+                            "transcript": transcript
+                        });
+                        if client_write
+                            .send(Message::Text(complete_msg.to_string()))
+                            .await
+                            .is_err()
+                        {
+                            break;
+                        }
+                        if client_write.send(Message::Binary(audio.to_vec())).await.is_err() {
+                            break;
+                        }
+                    }
+                    Err(e) => {
+                        tracing::error!("Pipeline error: {:?}", e);
+                        metrics::update(session_id, |s| s.errors += 1).await;
+                        let _ = client_write
+                            .send(Message::Text(
+                                serde_json::json!({"type": "error", "message": e.to_string()})
+                                    .to_string(),
+                            ))
+                            .await;
+                    }
                 }
             }
             Ok(Message::Text(text)) => {
-                // Handle text messages (e.g., pings)
                 if let Ok(v) = serde_json::from_str::<serde_json::Value>(&text) {
                     if v.get("type").and_then(|t| t.as_str()) == Some("ping") {
-                        let _ = client_ws
-                            .send(Message::Text(
-                                serde_json::json!({"type": "pong"}).to_string(),
-                            ))
+                        let _ = client_write
+                            .send(Message::Text(serde_json::json!({"type": "pong"}).to_string()))
+                            .await;
+                    }
+                }
+            }
+            Ok(Message::Close(_)) => {
+                tracing::info!("Client disconnected");
+                break;
+            }
+            Err(e) => {
+                tracing::error!("Client WebSocket error: {}", e);
+                break;
+            }
+            _ => {}
+        }
+    }
+}
+
+type ClientWriter = Arc<Mutex<SplitSink<WebSocket, Message>>>;
+type OpenAiSocket = tokio_tungstenite::WebSocketStream<
+    tokio_tungstenite::MaybeTlsStream<proxy::PrefixedStream<tokio::net::TcpStream>>,
+>;
+type OpenAiWriter = SplitSink<OpenAiSocket, TungsteniteMessage>;
+type OpenAiReader = futures_util::stream::SplitStream<OpenAiSocket>;
+
+/// Reads client audio/control frames and forwards audio to OpenAI for the
+/// lifetime of the session, replying to pings directly.
+async fn forward_client_to_openai(
+    mut client_read: futures_util::stream::SplitStream<WebSocket>,
+    mut openai_write: OpenAiWriter,
+    client_write: ClientWriter,
+    session_id: SessionId,
+) {
+    while let Some(msg) = client_read.next().await {
+        match msg {
+            Ok(Message::Binary(audio_data)) => {
+                tracing::debug!("Received audio data: {} bytes", audio_data.len());
+                if audio_data.is_empty() {
+                    tracing::warn!("Audio data is empty!");
+                    continue;
+                }
+                metrics::update(session_id, |s| {
+                    s.audio_bytes_received += audio_data.len() as u64
+                })
+                .await;
+
+                let audio_base64 = base64::engine::general_purpose::STANDARD.encode(&audio_data);
+                let audio_append = AudioAppend::new(audio_base64);
+                let payload = match serde_json::to_string(&audio_append) {
+                    Ok(payload) => payload,
+                    Err(e) => {
+                        tracing::error!("Failed to serialize audio append: {}", e);
+                        metrics::update(session_id, |s| s.errors += 1).await;
+                        continue;
+                    }
+                };
+                if let Err(e) = openai_write.send(TungsteniteMessage::Text(payload)).await {
+                    tracing::warn!("Failed to forward audio to OpenAI: {}", e);
+                    metrics::update(session_id, |s| s.errors += 1).await;
+                    break;
+                }
+            }
+            Ok(Message::Text(text)) => {
+                if let Ok(v) = serde_json::from_str::<serde_json::Value>(&text) {
+                    if v.get("type").and_then(|t| t.as_str()) == Some("ping") {
+                        let _ = client_write
+                            .lock()
+                            .await
+                            .send(Message::Text(serde_json::json!({"type": "pong"}).to_string()))
                             .await;
                     }
                 }
@@ -127,95 +383,72 @@ async fn handle_client_socket(mut client_ws: WebSocket) {
             _ => {}
         }
     }
+
+    let _ = openai_write.close().await;
 }
 
-/// Connects to OpenAI, sends audio, and streams the response back to the client
-async fn handle_openai_stream(
-    client_ws: &mut WebSocket,
-    audio_data: Vec<u8>,
-) -> anyhow::Result<()> {
-    tracing::info!("Connecting to OpenAI Realtime API...");
-
-    // Create the connection request with headers
-    let mut request = OPENAI_REALTIME_URL.into_client_request()?;
-    let headers = request.headers_mut();
-    headers.insert(
-        "Authorization",
-        format!("Bearer {}", SETTINGS.openai_api_key).parse()?,
-    );
-    headers.insert("OpenAI-Beta", "realtime=v1".parse()?);
-
-    // Connect to OpenAI
-    let (openai_ws, _) = connect_async(request).await?;
-    tracing::info!("Connected to OpenAI Realtime API");
-
-    let (mut openai_write, mut openai_read) = openai_ws.split();
-
-    // 1. Send session configuration
-    let session_update = SessionUpdate::new();
-    openai_write
-        .send(TungsteniteMessage::Text(
-            serde_json::to_string(&session_update)?,
-        ))
-        .await?;
-
-    // 2. Send audio data
-    let audio_base64 = base64::engine::general_purpose::STANDARD.encode(&audio_data);
-    tracing::debug!(
-        "Sending audio buffer: {} chars base64 ({} bytes raw)",
-        audio_base64.len(),
-        audio_data.len()
-    );
-    let audio_append = AudioAppend::new(audio_base64);
-    openai_write
-        .send(TungsteniteMessage::Text(
-            serde_json::to_string(&audio_append)?,
-        ))
-        .await?;
-
-    // 3. Commit the audio buffer
-    let commit = Commit::default();
-    tracing::debug!("Committing audio buffer: {:?}", commit);
-    openai_write
-        .send(TungsteniteMessage::Text(serde_json::to_string(&commit)?))
-        .await?;
-
-    // 4. Create a response
-    let response_create = ResponseCreate::default();
-    openai_write
-        .send(TungsteniteMessage::Text(
-            serde_json::to_string(&response_create)?,
-        ))
-        .await?;
-
-    // 5. Stream responses back to client
+/// Streams events from the persistent OpenAI connection back to the client,
+/// collecting the transcript for each turn and resetting it once the turn
+/// completes so the next one starts clean.
+async fn forward_openai_to_client(
+    mut openai_read: OpenAiReader,
+    client_write: ClientWriter,
+    provider: Arc<dyn RealtimeProvider>,
+    session_id: SessionId,
+) {
     let mut transcript_chunks = Vec::new();
 
     while let Some(msg) = openai_read.next().await {
         match msg {
             Ok(TungsteniteMessage::Text(text)) => {
-                let event: OpenAIEvent = serde_json::from_str(&text)?;
+                let event = match provider.parse_event(&text) {
+                    Ok(event) => event,
+                    Err(e) => {
+                        tracing::warn!("Failed to parse OpenAI event: {}", e);
+                        metrics::update(session_id, |s| s.errors += 1).await;
+                        continue;
+                    }
+                };
                 tracing::debug!("Received event: {}", event.event_type);
+                metrics::update(session_id, |s| s.events_forwarded += 1).await;
 
                 // Forward all events to client for transparency
-                client_ws.send(Message::Text(text)).await?;
+                if client_write
+                    .lock()
+                    .await
+                    .send(Message::Text(event.raw.clone()))
+                    .await
+                    .is_err()
+                {
+                    break;
+                }
 
-                // Collect transcript
                 if event.event_type == "response.audio_transcript.delta" {
                     if let Some(delta) = event.delta {
+                        metrics::update(session_id, |s| {
+                            s.transcript_chars += delta.chars().count() as u64
+                        })
+                        .await;
                         transcript_chunks.push(delta);
                     }
                 }
 
-                // When response is done, signal completion
                 if event.event_type == "response.done" {
                     tracing::info!("Response complete");
                     let complete_msg = serde_json::json!({
                         "type": "response_complete",
                         "transcript": transcript_chunks.join("")
                     });
-                    client_ws.send(Message::Text(complete_msg.to_string())).await?;
-                    break;
+                    if client_write
+                        .lock()
+                        .await
+                        .send(Message::Text(complete_msg.to_string()))
+                        .await
+                        .is_err()
+                    {
+                        break;
+                    }
+                    transcript_chunks.clear();
                 }
             }
             Ok(TungsteniteMessage::Close(_)) => {
@@ -224,12 +457,10 @@ async fn handle_openai_stream(
             }
             Err(e) => {
                 tracing::warn!("OpenAI WebSocket error: {}", e);
-                return Err(e.into());
+                metrics::update(session_id, |s| s.errors += 1).await;
+                break;
             }
             _ => { /* Ignore other message types */ }
         }
     }
-
-    tracing::info!("OpenAI stream finished.");
-    Ok(())
 }
@@ -1,27 +1,39 @@
+use crate::config::SessionSettings;
 use serde::{Deserialize, Serialize};
 
 //--- Outgoing Messages (Client -> OpenAI) ---
 
 #[derive(Serialize, Debug)]
 #[serde(rename_all = "camelCase")]
-pub struct SessionUpdate<'a> {
+pub struct SessionUpdate {
     #[serde(rename = "type")]
-    pub event_type: &'a str,
-    pub session: SessionConfig<'a>,
+    pub event_type: &'static str,
+    pub session: SessionConfig,
 }
 
-impl<'a> SessionUpdate<'a> {
-    pub fn new() -> Self {
+impl SessionUpdate {
+    /// Build the session configuration sent to the provider from the
+    /// operator-configured session settings (`config.yaml` / env overrides).
+    pub fn from_settings(settings: &SessionSettings) -> Self {
         Self {
             event_type: "session.update",
             session: SessionConfig {
-                modalities: &["text", "audio"],
-                instructions: "You are a helpful AI assistant. Have a natural conversation with the user in English.",
-                voice: "alloy",
-                input_audio_format: "pcm16",
-                output_audio_format: "pcm16",
-                input_audio_transcription: TranscriptionConfig { model: "whisper-1" },
-                turn_detection: None,
+                modalities: settings.modalities.clone(),
+                instructions: settings.instructions.clone(),
+                voice: settings.voice.clone(),
+                input_audio_format: "pcm16".to_string(),
+                output_audio_format: "pcm16".to_string(),
+                input_audio_transcription: TranscriptionConfig {
+                    model: settings.transcription_model.clone(),
+                },
+                // Let the model detect end-of-speech itself so a single
+                // connection can carry back-to-back turns without the server
+                // manually committing the audio buffer after every chunk.
+                turn_detection: Some(serde_json::json!({
+                    "type": "server_vad",
+                    "threshold": 0.5,
+                    "silence_duration_ms": 500
+                })),
             },
         }
     }
@@ -29,20 +41,20 @@ impl<'a> SessionUpdate<'a> {
 
 #[derive(Serialize, Debug)]
 #[serde(rename_all = "camelCase")]
-pub struct SessionConfig<'a> {
-    pub modalities: &'a [&'a str],
-    pub instructions: &'a str,
-    pub voice: &'a str,
-    pub input_audio_format: &'a str,
-    pub output_audio_format: &'a str,
-    pub input_audio_transcription: TranscriptionConfig<'a>,
+pub struct SessionConfig {
+    pub modalities: Vec<String>,
+    pub instructions: String,
+    pub voice: String,
+    pub input_audio_format: String,
+    pub output_audio_format: String,
+    pub input_audio_transcription: TranscriptionConfig,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub turn_detection: Option<serde_json::Value>,
 }
 
 #[derive(Serialize, Debug)]
-pub struct TranscriptionConfig<'a> {
-    pub model: &'a str,
+pub struct TranscriptionConfig {
+    pub model: String,
 }
 
 #[derive(Serialize, Debug)]
@@ -65,53 +77,62 @@ impl AudioAppend {
     }
 }
 
-#[derive(Serialize, Debug)]
-pub struct Commit<'a> {
+//--- Incoming Messages (OpenAI -> Client) ---
+
+#[derive(Deserialize, Debug)]
+pub struct OpenAIEvent {
     #[serde(rename = "type")]
-    pub event_type: &'a str,
+    pub event_type: String,
+    #[serde(default)]
+    pub delta: Option<String>,
+    // Add other fields you might care about, e.g.
+    // pub text: Option<String>,
+    // pub sequence_id: Option<i32>,
 }
 
-impl<'a> Default for Commit<'a> {
-    fn default() -> Self {
-        Self {
-            event_type: "input_audio_buffer.commit",
-        }
-    }
+/// Provider-agnostic view of an event coming back from a realtime provider,
+/// so callers don't need to know the wire schema of any particular backend.
+#[derive(Debug, Clone)]
+pub struct ProviderEvent {
+    pub event_type: String,
+    pub delta: Option<String>,
+    /// The original text frame, forwarded to the client as-is for transparency.
+    pub raw: String,
 }
 
+//--- Pipeline mode (REST fallback: transcribe -> chat -> speech) ---
 
-#[derive(Serialize, Debug)]
-pub struct ResponseCreate<'a> {
-    #[serde(rename = "type")]
-    pub event_type: &'a str,
-    pub response: ResponseConfig<'a>,
+#[derive(Deserialize, Debug)]
+pub struct TranscriptionResponse {
+    pub text: String,
 }
 
-impl<'a> Default for ResponseCreate<'a> {
-    fn default() -> Self {
-        Self {
-            event_type: "response.create",
-            response: ResponseConfig {
-                modalities: &["text", "audio"],
-            },
-        }
-    }
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ChatMessage {
+    pub role: String,
+    pub content: String,
 }
 
 #[derive(Serialize, Debug)]
-pub struct ResponseConfig<'a> {
-    pub modalities: &'a [&'a str],
+pub struct ChatCompletionRequest {
+    pub model: String,
+    pub messages: Vec<ChatMessage>,
 }
 
-//--- Incoming Messages (OpenAI -> Client) ---
+#[derive(Deserialize, Debug)]
+pub struct ChatCompletionResponse {
+    pub choices: Vec<ChatCompletionChoice>,
+}
 
 #[derive(Deserialize, Debug)]
-pub struct OpenAIEvent {
-    #[serde(rename = "type")]
-    pub event_type: String,
-    #[serde(default)]
-    pub delta: Option<String>,
-    // Add other fields you might care about, e.g.
-    // pub text: Option<String>,
-    // pub sequence_id: Option<i32>,
+pub struct ChatCompletionChoice {
+    pub message: ChatMessage,
+}
+
+#[derive(Serialize, Debug)]
+pub struct SpeechRequest {
+    pub model: String,
+    pub input: String,
+    pub voice: String,
+    pub response_format: String,
 }
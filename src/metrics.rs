@@ -0,0 +1,53 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use tokio::sync::Mutex;
+
+/// Identifies a single client session for the lifetime of its connection.
+pub type SessionId = u64;
+
+static NEXT_SESSION_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Allocate a fresh, process-unique session id.
+pub fn next_session_id() -> SessionId {
+    NEXT_SESSION_ID.fetch_add(1, Ordering::Relaxed)
+}
+
+/// Observability counters tracked for a single client session.
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct SessionStats {
+    pub audio_bytes_received: u64,
+    pub events_forwarded: u64,
+    pub transcript_chars: u64,
+    pub connect_latency_ms: Option<u64>,
+    pub errors: u64,
+}
+
+type SharedStats = Arc<Mutex<HashMap<SessionId, SessionStats>>>;
+
+static SESSION_STATS: Lazy<SharedStats> = Lazy::new(|| Arc::new(Mutex::new(HashMap::new())));
+
+/// Start tracking a newly connected session.
+pub async fn register_session(id: SessionId) {
+    SESSION_STATS.lock().await.insert(id, SessionStats::default());
+}
+
+/// Stop tracking a session once its connection has ended.
+pub async fn remove_session(id: SessionId) {
+    SESSION_STATS.lock().await.remove(&id);
+}
+
+/// Apply an update to a tracked session's counters, if it's still tracked.
+pub async fn update(id: SessionId, f: impl FnOnce(&mut SessionStats)) {
+    if let Some(stats) = SESSION_STATS.lock().await.get_mut(&id) {
+        f(stats);
+    }
+}
+
+/// Snapshot all currently tracked sessions, e.g. to serve over `/stats`.
+pub async fn snapshot() -> HashMap<SessionId, SessionStats> {
+    SESSION_STATS.lock().await.clone()
+}
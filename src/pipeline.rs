@@ -0,0 +1,159 @@
+use bytes::Bytes;
+use once_cell::sync::Lazy;
+
+use crate::config::{Settings, SETTINGS};
+use crate::messages::{
+    ChatCompletionRequest, ChatCompletionResponse, ChatMessage, SpeechRequest,
+    TranscriptionResponse,
+};
+
+const API_BASE_URL: &str = "https://api.openai.com/v1";
+const CHAT_MODEL: &str = "gpt-4o-mini";
+const TTS_MODEL: &str = "tts-1";
+
+// Must match the `pcm16` input format `SessionUpdate::from_settings` negotiates
+// for the realtime path, since the WAV header built for Whisper describes the
+// same raw audio the client streams in either mode.
+const PCM_SAMPLE_RATE_HZ: u32 = 24_000;
+const PCM_CHANNELS: u16 = 1;
+const PCM_BITS_PER_SAMPLE: u16 = 16;
+
+// Built from `SETTINGS.proxy`/`connect_timeout` so requests to the
+// transcribe/chat/speech endpoints honor the same outbound proxy as the
+// realtime WebSocket path (see `proxy.rs`), instead of going direct.
+static HTTP_CLIENT: Lazy<reqwest::Client> = Lazy::new(|| {
+    let mut builder = reqwest::Client::builder().connect_timeout(SETTINGS.connect_timeout);
+    if let Some(proxy_url) = &SETTINGS.proxy {
+        match reqwest::Proxy::all(proxy_url) {
+            Ok(proxy) => builder = builder.proxy(proxy),
+            Err(e) => tracing::warn!("Ignoring invalid PROXY for pipeline HTTP client: {}", e),
+        }
+    }
+    builder
+        .build()
+        .expect("failed to build pipeline HTTP client")
+});
+
+/// Non-realtime fallback: transcribe the utterance with Whisper, get a reply
+/// from Chat Completions, then synthesize it with the TTS speech endpoint.
+/// Used when `Settings::mode` is `Mode::Pipeline`.
+pub async fn run_pipeline(settings: &Settings, audio_data: Vec<u8>) -> anyhow::Result<(String, Bytes)> {
+    let transcript = transcribe(settings, audio_data).await?;
+    tracing::debug!("Transcribed: {}", transcript);
+
+    let reply = chat(settings, &transcript).await?;
+    tracing::debug!("Chat reply: {}", reply);
+
+    let audio = synthesize_speech(settings, &reply).await?;
+    Ok((reply, audio))
+}
+
+async fn transcribe(settings: &Settings, audio_data: Vec<u8>) -> anyhow::Result<String> {
+    let wav_bytes = wrap_pcm16_as_wav(&audio_data);
+    let form = reqwest::multipart::Form::new()
+        .text("model", settings.session.transcription_model.clone())
+        .part(
+            "file",
+            reqwest::multipart::Part::bytes(wav_bytes)
+                .file_name("audio.wav")
+                .mime_str("audio/wav")?,
+        );
+
+    let response = HTTP_CLIENT
+        .post(format!("{API_BASE_URL}/audio/transcriptions"))
+        .bearer_auth(&settings.openai_api_key)
+        .multipart(form)
+        .send()
+        .await?
+        .error_for_status()?
+        .json::<TranscriptionResponse>()
+        .await?;
+
+    Ok(response.text)
+}
+
+async fn chat(settings: &Settings, transcript: &str) -> anyhow::Result<String> {
+    let request = ChatCompletionRequest {
+        model: CHAT_MODEL.to_string(),
+        messages: vec![
+            ChatMessage {
+                role: "system".to_string(),
+                content: settings.session.instructions.clone(),
+            },
+            ChatMessage {
+                role: "user".to_string(),
+                content: transcript.to_string(),
+            },
+        ],
+    };
+
+    let response = HTTP_CLIENT
+        .post(format!("{API_BASE_URL}/chat/completions"))
+        .bearer_auth(&settings.openai_api_key)
+        .json(&request)
+        .send()
+        .await?
+        .error_for_status()?
+        .json::<ChatCompletionResponse>()
+        .await?;
+
+    response
+        .choices
+        .into_iter()
+        .next()
+        .map(|choice| choice.message.content)
+        .ok_or_else(|| anyhow::anyhow!("chat completion returned no choices"))
+}
+
+async fn synthesize_speech(settings: &Settings, text: &str) -> anyhow::Result<Bytes> {
+    let request = SpeechRequest {
+        model: TTS_MODEL.to_string(),
+        input: text.to_string(),
+        voice: settings.session.voice.clone(),
+        // The client decodes raw PCM16 frames (same as the realtime path),
+        // not the MP3 the TTS endpoint defaults to.
+        response_format: "pcm".to_string(),
+    };
+
+    let audio = HTTP_CLIENT
+        .post(format!("{API_BASE_URL}/audio/speech"))
+        .bearer_auth(&settings.openai_api_key)
+        .json(&request)
+        .send()
+        .await?
+        .error_for_status()?
+        .bytes()
+        .await?;
+
+    Ok(audio)
+}
+
+/// Wraps headerless PCM16 samples (as captured by the client and forwarded
+/// unmodified over the WebSocket) in a minimal RIFF/WAVE container, since
+/// Whisper's transcription endpoint sniffs the audio format from the bytes
+/// rather than trusting the multipart part's file name.
+fn wrap_pcm16_as_wav(pcm: &[u8]) -> Vec<u8> {
+    let byte_rate = PCM_SAMPLE_RATE_HZ * u32::from(PCM_CHANNELS) * u32::from(PCM_BITS_PER_SAMPLE) / 8;
+    let block_align = PCM_CHANNELS * (PCM_BITS_PER_SAMPLE / 8);
+    let data_len = pcm.len() as u32;
+
+    let mut wav = Vec::with_capacity(44 + pcm.len());
+    wav.extend_from_slice(b"RIFF");
+    wav.extend_from_slice(&(36 + data_len).to_le_bytes());
+    wav.extend_from_slice(b"WAVE");
+
+    wav.extend_from_slice(b"fmt ");
+    wav.extend_from_slice(&16u32.to_le_bytes()); // fmt chunk size
+    wav.extend_from_slice(&1u16.to_le_bytes()); // PCM format tag
+    wav.extend_from_slice(&PCM_CHANNELS.to_le_bytes());
+    wav.extend_from_slice(&PCM_SAMPLE_RATE_HZ.to_le_bytes());
+    wav.extend_from_slice(&byte_rate.to_le_bytes());
+    wav.extend_from_slice(&block_align.to_le_bytes());
+    wav.extend_from_slice(&PCM_BITS_PER_SAMPLE.to_le_bytes());
+
+    wav.extend_from_slice(b"data");
+    wav.extend_from_slice(&data_len.to_le_bytes());
+    wav.extend_from_slice(pcm);
+
+    wav
+}
@@ -0,0 +1,73 @@
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::handshake::client::Request as ClientRequest;
+
+use crate::config::{SessionSettings, Settings};
+use crate::messages::{OpenAIEvent, ProviderEvent, SessionUpdate};
+
+const DEFAULT_REALTIME_BASE_URL: &str = "wss://api.openai.com/v1/realtime";
+
+/// Abstracts over the realtime voice API a client connects to, so the rest of
+/// the bridge doesn't need to know whether it's talking to OpenAI directly,
+/// an Azure OpenAI deployment, or any other compatible gateway.
+pub trait RealtimeProvider: Send + Sync {
+    /// Build the WebSocket upgrade request (URL + auth headers) for this provider.
+    fn connect_request(&self) -> anyhow::Result<ClientRequest>;
+
+    /// Serialize the initial `session.update` event sent right after connecting.
+    fn session_update(&self) -> anyhow::Result<String>;
+
+    /// Parse a raw text frame from the provider into a provider-agnostic event.
+    fn parse_event(&self, text: &str) -> anyhow::Result<ProviderEvent>;
+}
+
+/// `RealtimeProvider` implementation for OpenAI's Realtime API, and for any
+/// OpenAI-compatible gateway (e.g. Azure OpenAI) reachable via a custom `base_url`.
+pub struct OpenAIProvider {
+    api_key: String,
+    base_url: String,
+    model: String,
+    session: SessionSettings,
+}
+
+impl OpenAIProvider {
+    pub fn new(settings: &Settings) -> Self {
+        Self {
+            api_key: settings.openai_api_key.clone(),
+            base_url: settings
+                .base_url
+                .clone()
+                .unwrap_or_else(|| DEFAULT_REALTIME_BASE_URL.to_string()),
+            model: settings.session.model.clone(),
+            session: settings.session.clone(),
+        }
+    }
+}
+
+impl RealtimeProvider for OpenAIProvider {
+    fn connect_request(&self) -> anyhow::Result<ClientRequest> {
+        let url = format!("{}?model={}", self.base_url, self.model);
+        let mut request = url.into_client_request()?;
+        let headers = request.headers_mut();
+        headers.insert(
+            "Authorization",
+            format!("Bearer {}", self.api_key).parse()?,
+        );
+        headers.insert("OpenAI-Beta", "realtime=v1".parse()?);
+        Ok(request)
+    }
+
+    fn session_update(&self) -> anyhow::Result<String> {
+        Ok(serde_json::to_string(&SessionUpdate::from_settings(
+            &self.session,
+        ))?)
+    }
+
+    fn parse_event(&self, text: &str) -> anyhow::Result<ProviderEvent> {
+        let event: OpenAIEvent = serde_json::from_str(text)?;
+        Ok(ProviderEvent {
+            event_type: event.event_type,
+            delta: event.delta,
+            raw: text.to_string(),
+        })
+    }
+}
@@ -0,0 +1,340 @@
+use std::pin::Pin;
+use std::task::{Context as TaskContext, Poll};
+use std::time::Duration;
+
+use anyhow::Context;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, ReadBuf};
+use tokio::net::TcpStream;
+use tokio_tungstenite::tungstenite::handshake::client::Request as ClientRequest;
+use tokio_tungstenite::tungstenite::http::Response;
+use tokio_tungstenite::{client_async_tls, MaybeTlsStream, WebSocketStream};
+
+/// A stream that replays a handful of already-read bytes before falling
+/// through to the underlying socket. Used so bytes the CONNECT response read
+/// past its `\r\n\r\n` terminator (e.g. the start of the TLS handshake,
+/// coalesced into the same read as the proxy's reply) aren't lost.
+pub struct PrefixedStream<S> {
+    prefix: Vec<u8>,
+    prefix_pos: usize,
+    inner: S,
+}
+
+impl<S> PrefixedStream<S> {
+    fn new(prefix: Vec<u8>, inner: S) -> Self {
+        Self {
+            prefix,
+            prefix_pos: 0,
+            inner,
+        }
+    }
+}
+
+impl<S: AsyncRead + Unpin> AsyncRead for PrefixedStream<S> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut TaskContext<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        let this = self.get_mut();
+        if this.prefix_pos < this.prefix.len() {
+            let remaining = &this.prefix[this.prefix_pos..];
+            let n = remaining.len().min(buf.remaining());
+            buf.put_slice(&remaining[..n]);
+            this.prefix_pos += n;
+            return Poll::Ready(Ok(()));
+        }
+        Pin::new(&mut this.inner).poll_read(cx, buf)
+    }
+}
+
+impl<S: AsyncWrite + Unpin> AsyncWrite for PrefixedStream<S> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut TaskContext<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        Pin::new(&mut self.get_mut().inner).poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut TaskContext<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut TaskContext<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_shutdown(cx)
+    }
+}
+
+type OpenAiStream = WebSocketStream<MaybeTlsStream<PrefixedStream<TcpStream>>>;
+
+/// Which tunneling protocol a configured `proxy` URL speaks, selected by its
+/// scheme (`http://`/`https://` vs `socks5://`/`socks5h://`).
+enum ProxyScheme {
+    Http,
+    Socks5,
+}
+
+/// A parsed `proxy` URL: where to dial, which protocol to speak to it, and
+/// any `user:pass@` credentials. Only the SOCKS5 path authenticates with the
+/// credentials; the HTTP CONNECT request is sent without `Proxy-Authorization`.
+struct ProxyTarget {
+    scheme: ProxyScheme,
+    host: String,
+    port: u16,
+    credentials: Option<(String, String)>,
+}
+
+fn parse_proxy_url(proxy_url: &str) -> anyhow::Result<ProxyTarget> {
+    let (scheme_str, rest) = proxy_url
+        .split_once("://")
+        .context("proxy URL missing scheme")?;
+    let scheme = match scheme_str {
+        "http" | "https" => ProxyScheme::Http,
+        "socks5" | "socks5h" => ProxyScheme::Socks5,
+        other => anyhow::bail!("unsupported proxy scheme: {other}"),
+    };
+
+    let (credentials, authority) = match rest.split_once('@') {
+        Some((userinfo, authority)) => {
+            let (user, pass) = userinfo.split_once(':').unwrap_or((userinfo, ""));
+            (Some((user.to_string(), pass.to_string())), authority)
+        }
+        None => (None, rest),
+    };
+
+    let authority: http::uri::Authority =
+        authority.parse().context("invalid proxy host/port")?;
+    let host = authority.host().to_string();
+    let port = authority.port_u16().unwrap_or(match scheme {
+        ProxyScheme::Http => 8080,
+        ProxyScheme::Socks5 => 1080,
+    });
+
+    Ok(ProxyTarget {
+        scheme,
+        host,
+        port,
+        credentials,
+    })
+}
+
+/// Connects to the realtime endpoint in `request`, either directly or (when
+/// `proxy_url` is set) by tunneling through an HTTP CONNECT or SOCKS5 proxy
+/// first, for deployments behind a corporate firewall that can't reach
+/// `api.openai.com` directly. Both the direct and proxied paths share a
+/// single `connect_timeout` budget so a proxied session can't take longer to
+/// fail than a direct one with the same config.
+pub async fn connect(
+    request: ClientRequest,
+    proxy_url: Option<&str>,
+    connect_timeout: Duration,
+) -> anyhow::Result<(OpenAiStream, Response<Option<Vec<u8>>>)> {
+    tokio::time::timeout(connect_timeout, async move {
+        match proxy_url {
+            Some(proxy_url) => {
+                let target = parse_proxy_url(proxy_url)?;
+                match target.scheme {
+                    ProxyScheme::Http => {
+                        connect_via_http_proxy(request, &target.host, target.port).await
+                    }
+                    ProxyScheme::Socks5 => {
+                        connect_via_socks5_proxy(
+                            request,
+                            &target.host,
+                            target.port,
+                            target.credentials,
+                        )
+                        .await
+                    }
+                }
+            }
+            None => connect_direct(request).await,
+        }
+    })
+    .await
+    .context("timed out connecting to OpenAI Realtime API")?
+}
+
+async fn connect_direct(request: ClientRequest) -> anyhow::Result<(OpenAiStream, Response<Option<Vec<u8>>>)> {
+    let host = request.uri().host().context("request URI missing host")?.to_string();
+    let port = request.uri().port_u16().unwrap_or(443);
+
+    let stream = TcpStream::connect((host.as_str(), port)).await?;
+    let (ws_stream, response) = client_async_tls(request, PrefixedStream::new(Vec::new(), stream)).await?;
+    Ok((ws_stream, response))
+}
+
+async fn connect_via_http_proxy(
+    request: ClientRequest,
+    proxy_host: &str,
+    proxy_port: u16,
+) -> anyhow::Result<(OpenAiStream, Response<Option<Vec<u8>>>)> {
+    let target_host = request
+        .uri()
+        .host()
+        .context("request URI missing host")?
+        .to_string();
+    let target_port = request.uri().port_u16().unwrap_or(443);
+
+    let mut stream = TcpStream::connect((proxy_host, proxy_port)).await?;
+
+    let connect_request = format!(
+        "CONNECT {host}:{port} HTTP/1.1\r\nHost: {host}:{port}\r\n\r\n",
+        host = target_host,
+        port = target_port
+    );
+    stream.write_all(connect_request.as_bytes()).await?;
+
+    let (header, leftover) = read_connect_response(&mut stream).await?;
+    let status_line = header.lines().next().unwrap_or_default();
+    if !status_line.contains("200") {
+        anyhow::bail!("proxy CONNECT to {target_host}:{target_port} failed: {status_line}");
+    }
+
+    let (ws_stream, response) =
+        client_async_tls(request, PrefixedStream::new(leftover, stream)).await?;
+
+    Ok((ws_stream, response))
+}
+
+/// Tunnels through a SOCKS5 proxy (RFC 1928) to `request`'s host, offering
+/// username/password auth (RFC 1929) when `credentials` are configured and
+/// falling back to "no auth" otherwise.
+async fn connect_via_socks5_proxy(
+    request: ClientRequest,
+    proxy_host: &str,
+    proxy_port: u16,
+    credentials: Option<(String, String)>,
+) -> anyhow::Result<(OpenAiStream, Response<Option<Vec<u8>>>)> {
+    const SOCKS_VERSION: u8 = 0x05;
+    const METHOD_NO_AUTH: u8 = 0x00;
+    const METHOD_USER_PASS: u8 = 0x02;
+    const METHOD_NONE_ACCEPTABLE: u8 = 0xff;
+
+    let target_host = request
+        .uri()
+        .host()
+        .context("request URI missing host")?
+        .to_string();
+    let target_port = request.uri().port_u16().unwrap_or(443);
+
+    let mut stream = TcpStream::connect((proxy_host, proxy_port)).await?;
+
+    let methods: &[u8] = if credentials.is_some() {
+        &[METHOD_NO_AUTH, METHOD_USER_PASS]
+    } else {
+        &[METHOD_NO_AUTH]
+    };
+    let mut greeting = vec![SOCKS_VERSION, methods.len() as u8];
+    greeting.extend_from_slice(methods);
+    stream.write_all(&greeting).await?;
+
+    let mut method_reply = [0u8; 2];
+    stream
+        .read_exact(&mut method_reply)
+        .await
+        .context("reading SOCKS5 method selection")?;
+    if method_reply[0] != SOCKS_VERSION {
+        anyhow::bail!("proxy at {proxy_host}:{proxy_port} is not a SOCKS5 proxy");
+    }
+    match method_reply[1] {
+        METHOD_NO_AUTH => {}
+        METHOD_USER_PASS => {
+            let (user, pass) = credentials.as_ref().context(
+                "SOCKS5 proxy requires username/password authentication but none were configured",
+            )?;
+            let mut auth = vec![0x01, user.len() as u8];
+            auth.extend_from_slice(user.as_bytes());
+            auth.push(pass.len() as u8);
+            auth.extend_from_slice(pass.as_bytes());
+            stream.write_all(&auth).await?;
+
+            let mut auth_reply = [0u8; 2];
+            stream
+                .read_exact(&mut auth_reply)
+                .await
+                .context("reading SOCKS5 authentication reply")?;
+            if auth_reply[1] != 0x00 {
+                anyhow::bail!("SOCKS5 proxy rejected username/password authentication");
+            }
+        }
+        METHOD_NONE_ACCEPTABLE => {
+            anyhow::bail!("SOCKS5 proxy rejected all offered authentication methods")
+        }
+        other => anyhow::bail!("SOCKS5 proxy selected unsupported auth method {other}"),
+    }
+
+    // CONNECT request with a domain-name address type, so the proxy (not us)
+    // resolves `target_host`.
+    let mut connect_req = vec![SOCKS_VERSION, 0x01, 0x00, 0x03, target_host.len() as u8];
+    connect_req.extend_from_slice(target_host.as_bytes());
+    connect_req.extend_from_slice(&target_port.to_be_bytes());
+    stream.write_all(&connect_req).await?;
+
+    let mut reply_head = [0u8; 4];
+    stream
+        .read_exact(&mut reply_head)
+        .await
+        .context("reading SOCKS5 CONNECT reply")?;
+    if reply_head[1] != 0x00 {
+        anyhow::bail!(
+            "SOCKS5 CONNECT to {target_host}:{target_port} failed with code {}",
+            reply_head[1]
+        );
+    }
+    // Discard the bound address/port that follows; we only dial `stream`,
+    // which is already the right socket.
+    let bound_addr_len = match reply_head[3] {
+        0x01 => 4,
+        0x04 => 16,
+        0x03 => {
+            let mut len_byte = [0u8; 1];
+            stream.read_exact(&mut len_byte).await?;
+            len_byte[0] as usize
+        }
+        other => anyhow::bail!("SOCKS5 reply used unsupported address type {other}"),
+    };
+    let mut bound_addr = vec![0u8; bound_addr_len + 2];
+    stream
+        .read_exact(&mut bound_addr)
+        .await
+        .context("reading SOCKS5 bound address")?;
+
+    let (ws_stream, response) =
+        client_async_tls(request, PrefixedStream::new(Vec::new(), stream)).await?;
+
+    Ok((ws_stream, response))
+}
+
+/// Reads the proxy's CONNECT response, looping until the `\r\n\r\n` header
+/// terminator shows up, since a real proxy's status line and headers can
+/// arrive split across more than one TCP segment. Returns the header text and
+/// any bytes read past the terminator, which belong to whatever comes next
+/// on the tunnel and must not be thrown away.
+async fn read_connect_response(stream: &mut TcpStream) -> anyhow::Result<(String, Vec<u8>)> {
+    let mut buf = Vec::new();
+    let mut chunk = [0u8; 1024];
+
+    loop {
+        let n = stream
+            .read(&mut chunk)
+            .await
+            .context("reading CONNECT response from proxy")?;
+        if n == 0 {
+            anyhow::bail!("proxy closed the connection before completing the CONNECT response");
+        }
+        buf.extend_from_slice(&chunk[..n]);
+
+        if let Some(header_end) = find_header_terminator(&buf) {
+            let leftover = buf[header_end..].to_vec();
+            buf.truncate(header_end);
+            return Ok((String::from_utf8_lossy(&buf).into_owned(), leftover));
+        }
+    }
+}
+
+fn find_header_terminator(buf: &[u8]) -> Option<usize> {
+    buf.windows(4)
+        .position(|window| window == b"\r\n\r\n")
+        .map(|pos| pos + 4)
+}